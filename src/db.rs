@@ -0,0 +1,158 @@
+//! SQLite-backed persistence for stored strings, replacing the old in-memory
+//! `HashMap` so data survives a restart and filtering can be pushed into SQL.
+
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use std::collections::HashMap;
+use std::env;
+
+use crate::{FilterQuery, StringData, StringProperties};
+
+pub type Pool = SqlitePool;
+
+const DEFAULT_DATABASE_URL: &str = "sqlite://strings.db";
+
+pub async fn init_pool() -> SqlitePool {
+    let database_url = env::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string());
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect(&database_url)
+        .await
+        .expect("failed to connect to DATABASE_URL");
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS strings (
+            id TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            length INTEGER NOT NULL,
+            byte_length INTEGER NOT NULL,
+            is_palindrome INTEGER NOT NULL,
+            unique_characters INTEGER NOT NULL,
+            word_count INTEGER NOT NULL,
+            character_frequency_map TEXT NOT NULL,
+            language TEXT,
+            language_confidence REAL NOT NULL
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .expect("failed to create strings table");
+
+    pool
+}
+
+fn row_to_string_data(row: sqlx::sqlite::SqliteRow) -> StringData {
+    let frequency_json: String = row.get("character_frequency_map");
+    let character_frequency_map: HashMap<String, usize> =
+        serde_json::from_str(&frequency_json).unwrap_or_default();
+    let length = row.get::<i64, _>("length") as usize;
+    StringData {
+        id: row.get("id"),
+        value: row.get("value"),
+        created_at: row.get::<DateTime<Utc>, _>("created_at"),
+        properties: StringProperties {
+            length,
+            byte_length: row.get::<i64, _>("byte_length") as usize,
+            grapheme_length: length,
+            is_palindrome: row.get("is_palindrome"),
+            unique_characters: row.get::<i64, _>("unique_characters") as usize,
+            word_count: row.get::<i64, _>("word_count") as usize,
+            sha256_hash: row.get("id"),
+            character_frequency_map,
+            language: row.get("language"),
+            language_confidence: row.get("language_confidence"),
+        },
+    }
+}
+
+pub async fn insert_string(pool: &SqlitePool, data: &StringData) -> Result<(), sqlx::Error> {
+    let frequency_json = serde_json::to_string(&data.properties.character_frequency_map)
+        .expect("character frequency map is always serializable");
+    let length = data.properties.length as i64;
+    let byte_length = data.properties.byte_length as i64;
+    let unique_characters = data.properties.unique_characters as i64;
+    let word_count = data.properties.word_count as i64;
+    sqlx::query(
+        r#"
+        INSERT INTO strings (id, value, created_at, length, byte_length, is_palindrome, unique_characters, word_count, character_frequency_map, language, language_confidence)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&data.id)
+    .bind(&data.value)
+    .bind(data.created_at)
+    .bind(length)
+    .bind(byte_length)
+    .bind(data.properties.is_palindrome)
+    .bind(unique_characters)
+    .bind(word_count)
+    .bind(frequency_json)
+    .bind(&data.properties.language)
+    .bind(data.properties.language_confidence)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Whether `err` is a `UNIQUE constraint failed` violation, so callers can
+/// distinguish a legitimate duplicate-value insert from a real DB failure
+/// without depending on `sqlx` error internals themselves.
+pub fn is_unique_violation(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(db_err) if db_err.is_unique_violation())
+}
+
+pub async fn get_by_id(pool: &SqlitePool, id: &str) -> Result<Option<StringData>, sqlx::Error> {
+    let row = sqlx::query("SELECT * FROM strings WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(row_to_string_data))
+}
+
+pub async fn delete_by_id(pool: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM strings WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Pushes every predicate present on `filter` down into the `WHERE` clause so
+/// filtering scales past what fits comfortably in memory.
+pub async fn filter_strings(
+    pool: &SqlitePool,
+    filter: &FilterQuery,
+) -> Result<Vec<StringData>, sqlx::Error> {
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM strings WHERE 1 = 1");
+
+    if let Some(pal) = filter.is_palindrome {
+        builder.push(" AND is_palindrome = ").push_bind(pal);
+    }
+    if let Some(min_l) = filter.min_length {
+        builder.push(" AND length >= ").push_bind(min_l as i64);
+    }
+    if let Some(max_l) = filter.max_length {
+        builder.push(" AND length <= ").push_bind(max_l as i64);
+    }
+    if let Some(wc) = filter.word_count {
+        builder.push(" AND word_count = ").push_bind(wc as i64);
+    }
+    if let Some(ch) = &filter.contains_character {
+        builder.push(" AND instr(value, ").push_bind(ch.clone()).push(") > 0");
+    }
+    if let Some(lang) = &filter.language {
+        builder.push(" AND language = ").push_bind(lang.clone());
+    }
+
+    let rows = builder.build().fetch_all(pool).await?;
+    Ok(rows.into_iter().map(row_to_string_data).collect())
+}
+
+pub async fn all_strings(pool: &SqlitePool) -> Result<Vec<StringData>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM strings").fetch_all(pool).await?;
+    Ok(rows.into_iter().map(row_to_string_data).collect())
+}
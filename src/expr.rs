@@ -0,0 +1,480 @@
+//! A small expression language for filtering stored strings, e.g.
+//! `length > 10 && is_palindrome && contains(value, "z")`.
+//!
+//! This module is a classic tokenizer -> recursive-descent parser ->
+//! tree-walking evaluator pipeline. Precedence from loosest to tightest:
+//! `||`, then `&&`, then comparisons, then unary `!`.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::StringData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    UnexpectedChar(char),
+    UnterminatedString,
+    UnexpectedToken(String),
+    UnexpectedEof,
+    UnknownIdentifier(String),
+    UnknownFunction(String),
+    TypeMismatch(String),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::UnexpectedChar(c) => write!(f, "unexpected character '{c}'"),
+            ExprError::UnterminatedString => write!(f, "unterminated string literal"),
+            ExprError::UnexpectedToken(t) => write!(f, "unexpected token '{t}'"),
+            ExprError::UnexpectedEof => write!(f, "unexpected end of expression"),
+            ExprError::UnknownIdentifier(name) => write!(f, "unknown identifier '{name}'"),
+            ExprError::UnknownFunction(name) => write!(f, "unknown function '{name}'"),
+            ExprError::TypeMismatch(msg) => write!(f, "type mismatch: {msg}"),
+        }
+    }
+}
+
+// ---------------------------------------------------------------- Tokenizer
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    And,
+    Or,
+    Not,
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ExprError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' | '\r' => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '!' => {
+                if chars.get(i + 1) == Some(&'=') {
+                    tokens.push(Token::Neq);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Not);
+                    i += 1;
+                }
+            }
+            '=' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Eq);
+                i += 2;
+            }
+            '<' => {
+                if chars.get(i + 1) == Some(&'=') {
+                    tokens.push(Token::Lte);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Lt);
+                    i += 1;
+                }
+            }
+            '>' => {
+                if chars.get(i + 1) == Some(&'=') {
+                    tokens.push(Token::Gte);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Gt);
+                    i += 1;
+                }
+            }
+            '&' if chars.get(i + 1) == Some(&'&') => {
+                tokens.push(Token::And);
+                i += 2;
+            }
+            '|' if chars.get(i + 1) == Some(&'|') => {
+                tokens.push(Token::Or);
+                i += 2;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&c) => {
+                            s.push(c);
+                            i += 1;
+                        }
+                        None => return Err(ExprError::UnterminatedString),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while chars.get(i).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let num = text.parse::<f64>().map_err(|_| ExprError::UnexpectedToken(text))?;
+                tokens.push(Token::Number(num));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while chars.get(i).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+                tokens.push(Token::Ident(ident));
+            }
+            c => return Err(ExprError::UnexpectedChar(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+// --------------------------------------------------------------------- AST
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Literal {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinOp {
+    And,
+    Or,
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Expr {
+    Literal(Literal),
+    Variable(String),
+    BinaryOp {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: &'static str,
+        expr: Box<Expr>,
+    },
+    FnCall {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+// ------------------------------------------------------------------ Parser
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), ExprError> {
+        match self.advance() {
+            Some(ref t) if t == token => Ok(()),
+            Some(t) => Err(ExprError::UnexpectedToken(format!("{t:?}"))),
+            None => Err(ExprError::UnexpectedEof),
+        }
+    }
+
+    // or_expr ::= and_expr ( "||" and_expr )*
+    fn parse_or(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_and()?;
+        while matches!(self.peek(), Some(Token::Or)) {
+            self.advance();
+            let rhs = self.parse_and()?;
+            lhs = Expr::BinaryOp {
+                op: BinOp::Or,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    // and_expr ::= cmp_expr ( "&&" cmp_expr )*
+    fn parse_and(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_comparison()?;
+        while matches!(self.peek(), Some(Token::And)) {
+            self.advance();
+            let rhs = self.parse_comparison()?;
+            lhs = Expr::BinaryOp {
+                op: BinOp::And,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    // cmp_expr ::= unary ( ("==" | "!=" | "<" | "<=" | ">" | ">=") unary )?
+    fn parse_comparison(&mut self) -> Result<Expr, ExprError> {
+        let lhs = self.parse_unary()?;
+        let op = match self.peek() {
+            Some(Token::Eq) => BinOp::Eq,
+            Some(Token::Neq) => BinOp::Neq,
+            Some(Token::Lt) => BinOp::Lt,
+            Some(Token::Lte) => BinOp::Lte,
+            Some(Token::Gt) => BinOp::Gt,
+            Some(Token::Gte) => BinOp::Gte,
+            _ => return Ok(lhs),
+        };
+        self.advance();
+        let rhs = self.parse_unary()?;
+        Ok(Expr::BinaryOp {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+    }
+
+    // unary ::= "!" unary | primary
+    fn parse_unary(&mut self) -> Result<Expr, ExprError> {
+        if matches!(self.peek(), Some(Token::Not)) {
+            self.advance();
+            let expr = self.parse_unary()?;
+            return Ok(Expr::Unary {
+                op: "!",
+                expr: Box::new(expr),
+            });
+        }
+        self.parse_primary()
+    }
+
+    // primary ::= number | string | "(" or_expr ")" | ident ["(" args ")"]
+    fn parse_primary(&mut self) -> Result<Expr, ExprError> {
+        match self.advance() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(Literal::Number(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Literal::Str(s))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                self.expect(&Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => {
+                if matches!(self.peek(), Some(Token::LParen)) {
+                    self.advance();
+                    let mut args = Vec::new();
+                    if !matches!(self.peek(), Some(Token::RParen)) {
+                        loop {
+                            args.push(self.parse_or()?);
+                            if matches!(self.peek(), Some(Token::Comma)) {
+                                self.advance();
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                    self.expect(&Token::RParen)?;
+                    Ok(Expr::FnCall { name, args })
+                } else if name == "true" {
+                    Ok(Expr::Literal(Literal::Bool(true)))
+                } else if name == "false" {
+                    Ok(Expr::Literal(Literal::Bool(false)))
+                } else {
+                    Ok(Expr::Variable(name))
+                }
+            }
+            Some(t) => Err(ExprError::UnexpectedToken(format!("{t:?}"))),
+            None => Err(ExprError::UnexpectedEof),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ExprError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(ExprError::UnexpectedToken(format!("{:?}", parser.tokens[parser.pos])));
+    }
+    Ok(expr)
+}
+
+// ---------------------------------------------------------------- Evaluator
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn as_bool(&self) -> Result<bool, ExprError> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            other => Err(ExprError::TypeMismatch(format!("expected bool, found {other:?}"))),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, ExprError> {
+        match self {
+            Value::Str(s) => Ok(s),
+            other => Err(ExprError::TypeMismatch(format!("expected string, found {other:?}"))),
+        }
+    }
+}
+
+fn variable(data: &StringData, name: &str) -> Result<Value, ExprError> {
+    match name {
+        "length" => Ok(Value::Number(data.properties.length as f64)),
+        "word_count" => Ok(Value::Number(data.properties.word_count as f64)),
+        "unique_characters" => Ok(Value::Number(data.properties.unique_characters as f64)),
+        "is_palindrome" => Ok(Value::Bool(data.properties.is_palindrome)),
+        "value" => Ok(Value::Str(data.value.clone())),
+        other => Err(ExprError::UnknownIdentifier(other.to_string())),
+    }
+}
+
+fn call(name: &str, args: &[Value]) -> Result<Value, ExprError> {
+    match (name, args) {
+        ("contains", [s, sub]) => Ok(Value::Bool(s.as_str()?.contains(sub.as_str()?))),
+        ("starts_with", [s, pre]) => Ok(Value::Bool(s.as_str()?.starts_with(pre.as_str()?))),
+        ("ends_with", [s, suf]) => Ok(Value::Bool(s.as_str()?.ends_with(suf.as_str()?))),
+        ("len", [s]) => Ok(Value::Number(s.as_str()?.chars().count() as f64)),
+        ("contains" | "starts_with" | "ends_with" | "len", _) => {
+            Err(ExprError::TypeMismatch(format!("wrong number of arguments to {name}")))
+        }
+        (other, _) => Err(ExprError::UnknownFunction(other.to_string())),
+    }
+}
+
+fn eval(expr: &Expr, data: &StringData) -> Result<Value, ExprError> {
+    match expr {
+        Expr::Literal(Literal::Number(n)) => Ok(Value::Number(*n)),
+        Expr::Literal(Literal::Str(s)) => Ok(Value::Str(s.clone())),
+        Expr::Literal(Literal::Bool(b)) => Ok(Value::Bool(*b)),
+        Expr::Variable(name) => variable(data, name),
+        Expr::Unary { op: "!", expr } => Ok(Value::Bool(!eval(expr, data)?.as_bool()?)),
+        Expr::Unary { op, .. } => Err(ExprError::UnexpectedToken(op.to_string())),
+        Expr::BinaryOp { op: BinOp::And, lhs, rhs } => {
+            if !eval(lhs, data)?.as_bool()? {
+                return Ok(Value::Bool(false));
+            }
+            Ok(Value::Bool(eval(rhs, data)?.as_bool()?))
+        }
+        Expr::BinaryOp { op: BinOp::Or, lhs, rhs } => {
+            if eval(lhs, data)?.as_bool()? {
+                return Ok(Value::Bool(true));
+            }
+            Ok(Value::Bool(eval(rhs, data)?.as_bool()?))
+        }
+        Expr::BinaryOp { op, lhs, rhs } => {
+            let lhs = eval(lhs, data)?;
+            let rhs = eval(rhs, data)?;
+            compare(op, &lhs, &rhs)
+        }
+        Expr::FnCall { name, args } => {
+            let values = args
+                .iter()
+                .map(|a| eval(a, data))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(name, &values)
+        }
+    }
+}
+
+fn compare(op: &BinOp, lhs: &Value, rhs: &Value) -> Result<Value, ExprError> {
+    let result = match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => match op {
+            BinOp::Eq => a == b,
+            BinOp::Neq => a != b,
+            BinOp::Lt => a < b,
+            BinOp::Lte => a <= b,
+            BinOp::Gt => a > b,
+            BinOp::Gte => a >= b,
+            BinOp::And | BinOp::Or => unreachable!("handled by short-circuit branch above"),
+        },
+        (Value::Str(a), Value::Str(b)) => match op {
+            BinOp::Eq => a == b,
+            BinOp::Neq => a != b,
+            _ => return Err(ExprError::TypeMismatch("strings only support == and !=".to_string())),
+        },
+        (Value::Bool(a), Value::Bool(b)) => match op {
+            BinOp::Eq => a == b,
+            BinOp::Neq => a != b,
+            _ => return Err(ExprError::TypeMismatch("booleans only support == and !=".to_string())),
+        },
+        (a, b) => return Err(ExprError::TypeMismatch(format!("cannot compare {a:?} and {b:?}"))),
+    };
+    Ok(Value::Bool(result))
+}
+
+/// Evaluates an already-parsed expression against `data`, returning whether it matches.
+pub fn matches(ast: &Expr, data: &StringData) -> Result<bool, ExprError> {
+    eval(ast, data)?.as_bool()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `#[serde(tag = "type")]` (internally tagged) can't represent a newtype
+    // variant wrapping a scalar, so every `Expr`/`Literal` leaf needs
+    // `content = "value"` or `serde_json::to_string` returns an error for them
+    // and these `expect`s panic.
+    #[test]
+    fn literal_and_variable_serialize() {
+        serde_json::to_string(&Expr::Variable("length".to_string())).expect("Variable should serialize");
+        serde_json::to_string(&Expr::Literal(Literal::Bool(true))).expect("Literal::Bool should serialize");
+        serde_json::to_string(&Expr::Literal(Literal::Number(1.5))).expect("Literal::Number should serialize");
+        serde_json::to_string(&Expr::Literal(Literal::Str("z".to_string())))
+            .expect("Literal::Str should serialize");
+    }
+
+    #[test]
+    fn parsed_expression_serializes() {
+        let expr = parse("length > 10 && contains(value, \"z\")").expect("should parse");
+        let json = serde_json::to_string(&expr).expect("parsed expression should serialize");
+        assert!(json.contains("\"type\":\"BinaryOp\""));
+    }
+}
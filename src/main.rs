@@ -1,27 +1,61 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
     response::Json,
     routing::{delete, get, post},
     Router,
 };
 use chrono::{DateTime, Utc};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use futures::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::env;
 use std::net::SocketAddr;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+mod db;
+mod expr;
+mod nlquery;
+
+/// The channel capacity for the newly-created-strings broadcast; slow SSE
+/// subscribers that fall this far behind just miss the oldest events.
+const STREAM_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Clone)]
+pub struct AppState {
+    pool: db::Pool,
+    new_strings: broadcast::Sender<StringData>,
+}
+
+/// Below this many graphemes, whatlang's language guess is unreliable, so we
+/// skip detection entirely rather than tag short strings with noise.
+const MIN_LANGUAGE_DETECTION_LENGTH: usize = 10;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct StringProperties {
+    /// Grapheme-cluster count (what `FilterQuery::min_length`/`max_length` compare against).
     pub length: usize,
+    /// Raw UTF-8 byte count of the NFC-normalized value, kept for callers that want it.
+    pub byte_length: usize,
+    /// Same as `length`, named explicitly for callers that care about grapheme semantics.
+    pub grapheme_length: usize,
     pub is_palindrome: bool,
     pub unique_characters: usize,
     pub word_count: usize,
     pub sha256_hash: String,
-    pub character_frequency_map: HashMap<char, usize>,
+    pub character_frequency_map: HashMap<String, usize>,
+    /// ISO 639-3 code (e.g. `eng`, `jpn`) detected by whatlang, or `None` below
+    /// [`MIN_LANGUAGE_DETECTION_LENGTH`] where detection isn't reliable.
+    pub language: Option<String>,
+    pub language_confidence: f64,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -44,6 +78,7 @@ pub struct FilterQuery {
     pub max_length: Option<usize>,
     pub word_count: Option<usize>,
     pub contains_character: Option<String>,
+    pub language: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -66,25 +101,82 @@ pub struct InterpretedQuery {
     pub parsed_filters: FilterQuery,
 }
 
-type AppState = Arc<Mutex<HashMap<String, StringData>>>;
+#[derive(Serialize)]
+pub struct NLParseErrorBody {
+    pub error: String,
+    pub span: (usize, usize),
+}
+
+#[derive(Serialize)]
+pub struct ExpressionFilterResponse {
+    pub data: Vec<StringData>,
+    pub count: usize,
+    pub interpreted_query: ExpressionInterpretedQuery,
+}
+
+#[derive(Serialize)]
+pub struct ExpressionInterpretedQuery {
+    pub original: String,
+    pub ast: expr::Expr,
+}
+
+#[derive(Serialize)]
+pub struct SearchResult {
+    pub data: StringData,
+    pub score: i64,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    pub count: usize,
+    pub query: String,
+}
 
 fn analyze_string(s: &str) -> StringProperties {
-    let length = s.len();
-    let is_palindrome = s.to_lowercase().chars().collect::<Vec<_>>() == s.to_lowercase().chars().rev().collect::<Vec<_>>();
-    let unique_characters = s.chars().filter(|c| c.is_alphabetic()).collect::<std::collections::HashSet<_>>().len();
-    let word_count = s.split_whitespace().count();
-    let sha256_hash = Sha256::digest(s.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let normalized: String = s.nfc().collect();
+    let byte_length = normalized.len();
+    let graphemes: Vec<&str> = normalized.graphemes(true).collect();
+    let length = graphemes.len();
+
+    let lowercase: String = normalized.to_lowercase();
+    let lowercase_graphemes: Vec<&str> = lowercase.graphemes(true).collect();
+    let is_palindrome = lowercase_graphemes.iter().eq(lowercase_graphemes.iter().rev());
+
+    let alphabetic_graphemes = graphemes
+        .iter()
+        .filter(|g| g.chars().next().is_some_and(|c| c.is_alphabetic()));
+    let unique_characters = alphabetic_graphemes.clone().collect::<HashSet<_>>().len();
+    let word_count = normalized.split_whitespace().count();
+    let sha256_hash = Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
     let mut character_frequency_map = HashMap::new();
-    for c in s.chars().filter(|c| c.is_alphabetic()) {
-        *character_frequency_map.entry(c).or_insert(0) += 1;
+    for g in alphabetic_graphemes {
+        *character_frequency_map.entry(g.to_string()).or_insert(0) += 1;
     }
+
+    let (language, language_confidence) = if length >= MIN_LANGUAGE_DETECTION_LENGTH {
+        match whatlang::detect(&normalized) {
+            Some(info) => (Some(info.lang().code().to_string()), info.confidence()),
+            None => (None, 0.0),
+        }
+    } else {
+        (None, 0.0)
+    };
+
     StringProperties {
         length,
+        byte_length,
+        grapheme_length: length,
         is_palindrome,
         unique_characters,
         word_count,
-        sha256_hash: sha256_hash.clone(),
+        sha256_hash,
         character_frequency_map,
+        language,
+        language_confidence,
     }
 }
 
@@ -92,19 +184,23 @@ async fn create_string(
     State(state): State<AppState>,
     Json(payload): Json<CreateStringRequest>,
 ) -> Result<(StatusCode, Json<StringData>), StatusCode> {
-    let properties = analyze_string(&payload.value);
+    let value: String = payload.value.nfc().collect();
+    let properties = analyze_string(&value);
     let id = properties.sha256_hash.clone();
-    let mut db = state.lock().await;
-    if db.contains_key(&id) {
-        return Err(StatusCode::CONFLICT);
-    }
     let data = StringData {
         id: id.clone(),
-        value: payload.value,
+        value,
         properties,
         created_at: Utc::now(),
     };
-    db.insert(id, data.clone());
+    db::insert_string(&state.pool, &data).await.map_err(|err| {
+        if db::is_unique_violation(&err) {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+    let _ = state.new_strings.send(data.clone());
     Ok((StatusCode::CREATED, Json(data)))
 }
 
@@ -114,62 +210,32 @@ async fn get_string(
 ) -> Result<Json<StringData>, StatusCode> {
     let properties = analyze_string(&value);
     let id = properties.sha256_hash.clone();
-    let db = state.lock().await;
-    if let Some(data) = db.get(&id) {
-        Ok(Json(data.clone()))
-    } else {
-        Err(StatusCode::NOT_FOUND)
+    match db::get_by_id(&state.pool, &id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        Some(data) => Ok(Json(data)),
+        None => Err(StatusCode::NOT_FOUND),
     }
 }
 
 async fn get_all_strings(
     State(state): State<AppState>,
-    Query(query): Query<FilterQuery>,
+    Query(mut query): Query<FilterQuery>,
 ) -> Result<Json<StringsResponse>, StatusCode> {
-    let db = state.lock().await;
-    let mut filtered = Vec::new();
-    for (_, data) in db.iter() {
-        let mut matches = true;
-        if let Some(pal) = query.is_palindrome {
-            if data.properties.is_palindrome != pal {
-                matches = false;
-            }
-        }
-        if let Some(min_l) = query.min_length {
-            if data.properties.length < min_l {
-                matches = false;
-            }
-        }
-        if let Some(max_l) = query.max_length {
-            if data.properties.length > max_l {
-                matches = false;
-            }
-        }
-        if let Some(wc) = query.word_count {
-            if data.properties.word_count != wc {
-                matches = false;
-            }
-        }
-        if let Some(ch) = &query.contains_character {
-            if ch.len() != 1 {
-                // invalid, but we'll handle in main probably
-            } else {
-                let ch = ch.chars().next().unwrap();
-                if !data.value.chars().any(|c| c == ch) {
-                    matches = false;
-                }
-            }
-        }
-        if matches {
-            filtered.push(data.clone());
-        }
-    }
-    let count = filtered.len();
     if let Some(ch) = &query.contains_character {
-        if ch.len() != 1 {
+        // Normalize to NFC so this matches the same form stored strings' `value` is
+        // saved in, whether the caller sent a precomposed or a base+combining character.
+        let normalized: String = ch.nfc().collect();
+        if normalized.graphemes(true).count() != 1 {
             return Err(StatusCode::BAD_REQUEST);
         }
+        query.contains_character = Some(normalized);
     }
+    let filtered = db::filter_strings(&state.pool, &query)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let count = filtered.len();
     Ok(Json(StringsResponse {
         data: filtered,
         count,
@@ -177,118 +243,55 @@ async fn get_all_strings(
     }))
 }
 
-fn parse_natural_language_query(query: &str) -> Result<FilterQuery, String> {
-    let words: Vec<&str> = query.split_whitespace().collect();
-    let mut filters = FilterQuery::default();
-
-    let mut i = 0;
-    while i < words.len() {
-        match words[i].to_lowercase().as_str() {
-            "all" => {
-                // skip
-                i += 1;
-            }
-            "single" => {
-                if i + 1 < words.len() && words[i + 1].to_lowercase() == "word" {
-                    filters.word_count = Some(1);
-                    i += 2;
-                } else {
-                    i += 1;
-                }
-            }
-            "palindrome" | "palindromic" => {
-                if let Some(prev) = words.get(i - 1) {
-                    if prev.to_lowercase() != "non" {
-                        filters.is_palindrome = Some(true);
-                    }
-                } else {
-                    filters.is_palindrome = Some(true);
-                }
-                i += 1;
-            }
-            "longer" => {
-                if i + 2 < words.len() && words[i + 1].to_lowercase() == "than" {
-                    if let Ok(num) = words[i + 2].parse::<usize>() {
-                        filters.min_length = Some(num + 1);
-                        i += 3;
-                    } else {
-                        i += 1;
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            "containing" | "contain" => {
-                if i + 3 < words.len() && words[i + 1].to_lowercase() == "the" && words[i + 2].to_lowercase() == "letter" {
-                    let ch = words[i + 3];
-                    if ch.len() == 1 {
-                        filters.contains_character = Some(ch.to_string());
-                        i += 4;
-                    } else {
-                        i += 1;
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            "first" => {
-                if i + 1 < words.len() && words[i + 1].to_lowercase() == "vowel" {
-                    filters.contains_character = Some("a".to_string());
-                    i += 2;
-                } else {
-                    i += 1;
-                }
-            }
-            _ => i += 1,
-        }
+/// Maps a handful of common English language names to the ISO 639-3 codes
+/// whatlang reports, for clauses like "all palindromes in english".
+pub(crate) fn language_code_from_name(name: &str) -> Option<&'static str> {
+    match name {
+        "english" => Some("eng"),
+        "japanese" => Some("jpn"),
+        "french" => Some("fra"),
+        "german" => Some("deu"),
+        "spanish" => Some("spa"),
+        "italian" => Some("ita"),
+        "portuguese" => Some("por"),
+        "russian" => Some("rus"),
+        "chinese" => Some("cmn"),
+        "korean" => Some("kor"),
+        _ => None,
     }
-
-    Ok(filters)
 }
 
 async fn filter_by_natural_language(
     State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<NaturalLanguageResponse>, StatusCode> {
-    let query = params.get("query").ok_or(StatusCode::BAD_REQUEST)?;
-    let parsed = parse_natural_language_query(query).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut filtered = Vec::new();
-    let db = state.lock().await;
-    for (_, data) in db.iter() {
-        let mut matches = true;
-        if let Some(pal) = parsed.is_palindrome {
-            if data.properties.is_palindrome != pal {
-                matches = false;
-            }
-        }
-        if let Some(min_l) = parsed.min_length {
-            if data.properties.length < min_l {
-                matches = false;
-            }
-        }
-        if let Some(max_l) = parsed.max_length {
-            if data.properties.length > max_l {
-                matches = false;
-            }
-        }
-        if let Some(wc) = parsed.word_count {
-            if data.properties.word_count != wc {
-                matches = false;
-            }
-        }
-        if let Some(ch) = &parsed.contains_character {
-            if ch.len() != 1 {
-            } else {
-                let ch = ch.chars().next().unwrap();
-                if !data.value.chars().any(|c| c == ch) {
-                    matches = false;
-                }
-            }
-        }
-        if matches {
-            filtered.push(data.clone());
-        }
-    }
+) -> Result<Json<NaturalLanguageResponse>, (StatusCode, Json<NLParseErrorBody>)> {
+    let query = params.get("query").ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(NLParseErrorBody {
+                error: "missing `query` parameter".to_string(),
+                span: (0, 0),
+            }),
+        )
+    })?;
+    let parsed = nlquery::parse(query).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(NLParseErrorBody {
+                error: e.message,
+                span: e.span,
+            }),
+        )
+    })?;
+    let filtered = db::filter_strings(&state.pool, &parsed).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(NLParseErrorBody {
+                error: "internal error".to_string(),
+                span: (0, 0),
+            }),
+        )
+    })?;
     let count = filtered.len();
     Ok(Json(NaturalLanguageResponse {
         data: filtered,
@@ -300,17 +303,112 @@ async fn filter_by_natural_language(
     }))
 }
 
+async fn filter_by_expression(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ExpressionFilterResponse>, StatusCode> {
+    let raw_expr = params.get("expr").ok_or(StatusCode::BAD_REQUEST)?;
+    let ast = expr::parse(raw_expr).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let all = db::all_strings(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut filtered = Vec::new();
+    for data in all {
+        if expr::matches(&ast, &data).map_err(|_| StatusCode::BAD_REQUEST)? {
+            filtered.push(data);
+        }
+    }
+    let count = filtered.len();
+    Ok(Json(ExpressionFilterResponse {
+        data: filtered,
+        count,
+        interpreted_query: ExpressionInterpretedQuery {
+            original: raw_expr.to_string(),
+            ast,
+        },
+    }))
+}
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+async fn search_strings(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<SearchResponse>, StatusCode> {
+    let query = params.get("q").ok_or(StatusCode::BAD_REQUEST)?;
+    let limit = match params.get("limit") {
+        Some(raw) => raw.parse::<usize>().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => DEFAULT_SEARCH_LIMIT,
+    };
+
+    let all = db::all_strings(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let matcher = SkimMatcherV2::default();
+    let mut results: Vec<SearchResult> = all
+        .into_iter()
+        .filter_map(|data| {
+            matcher
+                .fuzzy_match(&data.value, query)
+                .map(|score| SearchResult { data, score })
+        })
+        .collect();
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    results.truncate(limit);
+
+    let count = results.len();
+    Ok(Json(SearchResponse {
+        results,
+        count,
+        query: query.to_string(),
+    }))
+}
+
+/// Streams newly created strings as they're inserted. With `?history=true`,
+/// replays the current store as an initial `history` event before going live.
+async fn stream_strings(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let replay_history = params.get("history").map(|v| v == "true").unwrap_or(false);
+
+    let history_event = if replay_history {
+        let all = db::all_strings(&state.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let event = Event::default()
+            .event("history")
+            .json_data(&all)
+            .expect("Vec<StringData> always serializes");
+        Some(Ok(event))
+    } else {
+        None
+    };
+
+    let live = BroadcastStream::new(state.new_strings.subscribe()).filter_map(|msg| async move {
+        msg.ok().map(|data| {
+            Ok(Event::default()
+                .event("string")
+                .json_data(&data)
+                .expect("StringData always serializes"))
+        })
+    });
+
+    let combined = stream::iter(history_event).chain(live);
+    Ok(Sse::new(combined).keep_alive(KeepAlive::default()))
+}
+
 async fn delete_string(
     State(state): State<AppState>,
     Path(value): Path<String>,
 ) -> StatusCode {
     let properties = analyze_string(&value);
     let id = properties.sha256_hash;
-    let mut db = state.lock().await;
-    if db.remove(&id).is_some() {
-        StatusCode::NO_CONTENT
-    } else {
-        StatusCode::NOT_FOUND
+    match db::delete_by_id(&state.pool, &id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
 
@@ -321,7 +419,10 @@ async fn main() {
     let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
     let addr = format!("0.0.0.0:{}", port).parse::<SocketAddr>().unwrap();
 
-    let state: AppState = Arc::new(Mutex::new(HashMap::new()));
+    // Get the SQLite connection string from env, default to a local file
+    let pool = db::init_pool().await;
+    let (new_strings, _) = broadcast::channel(STREAM_CHANNEL_CAPACITY);
+    let state = AppState { pool, new_strings };
 
     let app = Router::new()
         .route("/", get(|| async { "Hello, World!" }))
@@ -330,6 +431,9 @@ async fn main() {
         .route("/strings/:value", get(get_string))
         .route("/strings", get(get_all_strings))
         .route("/strings/filter-by-natural-language", get(filter_by_natural_language))
+        .route("/strings/filter-by-expression", get(filter_by_expression))
+        .route("/strings/search", get(search_strings))
+        .route("/strings/stream", get(stream_strings))
         .route("/strings/:value", delete(delete_string))
         .with_state(state);
 
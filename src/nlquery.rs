@@ -0,0 +1,157 @@
+//! Pest-based parser for natural-language string filters, replacing the old
+//! word-by-word state machine with a formal grammar (see `nlquery.pest`).
+
+use pest::error::InputLocation;
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser as PestParser;
+
+use crate::{language_code_from_name, FilterQuery};
+
+#[derive(PestParser)]
+#[grammar = "nlquery.pest"]
+struct NLQueryParser;
+
+/// A structured parse failure, including the byte span of the offending text.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub span: (usize, usize),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at byte {}..{})", self.message, self.span.0, self.span.1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<pest::error::Error<Rule>> for ParseError {
+    fn from(err: pest::error::Error<Rule>) -> Self {
+        let span = match err.location {
+            InputLocation::Pos(p) => (p, p),
+            InputLocation::Span((start, end)) => (start, end),
+        };
+        ParseError {
+            message: err.to_string(),
+            span,
+        }
+    }
+}
+
+/// Parses a natural-language query into a `FilterQuery`, returning a
+/// structured error with the offending span on malformed input instead of
+/// silently ignoring unrecognized tokens.
+pub fn parse(input: &str) -> Result<FilterQuery, ParseError> {
+    let mut filters = FilterQuery::default();
+    let mut pairs = NLQueryParser::parse(Rule::query, input)?;
+    let query = pairs.next().expect("Rule::query always produces one pair");
+    for clause in query.into_inner() {
+        if clause.as_rule() == Rule::clause {
+            apply_clause(clause, &mut filters)?;
+        }
+    }
+    Ok(filters)
+}
+
+fn apply_clause(clause: Pair<Rule>, filters: &mut FilterQuery) -> Result<(), ParseError> {
+    let inner = clause
+        .into_inner()
+        .next()
+        .expect("clause always wraps exactly one alternative");
+    match inner.as_rule() {
+        Rule::non_palindrome => filters.is_palindrome = Some(false),
+        Rule::palindrome => filters.is_palindrome = Some(true),
+        Rule::longer_than => {
+            let n = parse_number(&inner)?;
+            filters.min_length = Some(number_too_large(&inner, n.checked_add(1))?);
+        }
+        Rule::shorter_than => {
+            let n = parse_number(&inner)?;
+            filters.max_length = Some(n.saturating_sub(1));
+        }
+        Rule::exactly_characters => {
+            let n = parse_number(&inner)?;
+            filters.min_length = Some(n);
+            filters.max_length = Some(n);
+        }
+        Rule::single_word => filters.word_count = Some(1),
+        Rule::n_words => {
+            let n = parse_number(&inner)?;
+            filters.word_count = Some(n);
+        }
+        Rule::containing_letter => {
+            let letter = inner
+                .into_inner()
+                .find(|p| p.as_rule() == Rule::letter)
+                .expect("containing_letter always has a letter");
+            filters.contains_character = Some(letter.as_str().to_string());
+        }
+        Rule::in_language => {
+            let name = inner
+                .into_inner()
+                .find(|p| p.as_rule() == Rule::language_name)
+                .expect("in_language always has a language_name");
+            let code = language_code_from_name(&name.as_str().to_lowercase()).ok_or_else(|| {
+                let span = name.as_span();
+                ParseError {
+                    message: format!("unknown language '{}'", name.as_str()),
+                    span: (span.start(), span.end()),
+                }
+            })?;
+            filters.language = Some(code.to_string());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_number(clause: &Pair<Rule>) -> Result<usize, ParseError> {
+    let number = clause
+        .clone()
+        .into_inner()
+        .find(|p| p.as_rule() == Rule::number)
+        .expect("numeric clause always has a number");
+    let span = number.as_span();
+    number.as_str().parse().map_err(|_| ParseError {
+        message: format!("number '{}' is too large", number.as_str()),
+        span: (span.start(), span.end()),
+    })
+}
+
+/// `longer than N` computes `N + 1` for `min_length`; report the same
+/// too-large error as `parse_number` if that addition overflows.
+fn number_too_large(clause: &Pair<Rule>, value: Option<usize>) -> Result<usize, ParseError> {
+    value.ok_or_else(|| {
+        let number = clause
+            .clone()
+            .into_inner()
+            .find(|p| p.as_rule() == Rule::number)
+            .expect("numeric clause always has a number");
+        let span = number.as_span();
+        ParseError {
+            message: format!("number '{}' is too large", number.as_str()),
+            span: (span.start(), span.end()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "and" is optional between clauses so natural phrasing like this (and the
+    // `in_language` example from the original natural-language-filter request)
+    // parses without a forced conjunction.
+    #[test]
+    fn clauses_without_and_parse() {
+        let filters = parse("all palindromes longer than 3 characters").expect("should parse");
+        assert_eq!(filters.is_palindrome, Some(true));
+        assert_eq!(filters.min_length, Some(4));
+
+        let filters = parse("all palindromes in english").expect("should parse");
+        assert_eq!(filters.is_palindrome, Some(true));
+        assert_eq!(filters.language.as_deref(), Some("eng"));
+    }
+}